@@ -0,0 +1,21 @@
+use std::fmt;
+
+/// Errors produced while compiling or running a query.
+///
+/// `Todo` is the catch-all for query shapes the planner does not support
+/// yet; as support lands, call sites that used to return it get a real
+/// variant instead.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    Todo,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Todo => write!(f, "unsupported query"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,7 @@
+pub mod error;
+pub mod parser;
+pub mod planner;
+
+pub use error::Error;
+pub use planner::optimize::Statistics;
+pub use planner::plan::QueryPlan;
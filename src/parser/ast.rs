@@ -0,0 +1,127 @@
+//! Parsed representation of a Cypher-flavored query.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Left,
+    Right,
+    Either,
+}
+
+/// A `*min..max` hop count annotation on an edge, e.g. `*1..3` or `*..`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HopRange {
+    pub min: usize,
+    pub max: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Literal<'a> {
+    String(&'a str),
+    Integer(i64),
+    Float(f64),
+    Bool(bool),
+    Null,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Ne,
+}
+
+/// A boolean expression appearing in a `WHERE` clause or an inline
+/// `{...}` property map.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr<'a> {
+    PropertyEquals {
+        target: &'a str,
+        key: &'a str,
+        value: Literal<'a>,
+    },
+    PropertyCompare {
+        target: &'a str,
+        key: &'a str,
+        op: CompareOp,
+        value: Literal<'a>,
+    },
+    PropertyExists {
+        target: &'a str,
+        key: &'a str,
+    },
+    And(Box<Expr<'a>>, Box<Expr<'a>>),
+    Or(Box<Expr<'a>>, Box<Expr<'a>>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NodeAnnotation<'a> {
+    pub name: Option<&'a str>,
+    pub label: Option<&'a str>,
+    pub properties: &'a [(&'a str, Literal<'a>)],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EdgeAnnotation<'a> {
+    pub name: Option<&'a str>,
+    pub label: Option<&'a str>,
+    pub properties: &'a [(&'a str, Literal<'a>)],
+    /// Present when the edge carries a `*min..max` hop count.
+    pub hops: Option<HopRange>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct NodeRef<'a> {
+    pub annotation: NodeAnnotation<'a>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct EdgeRef<'a> {
+    pub annotation: EdgeAnnotation<'a>,
+    pub direction: Direction,
+}
+
+#[derive(Debug, Clone)]
+pub struct MatchClause<'a> {
+    pub start: NodeRef<'a>,
+    pub edges: Vec<(EdgeRef<'a>, NodeRef<'a>)>,
+    /// Set for `p = (a)-[:R*]->(b)`, binding the whole matched pattern to
+    /// `p` rather than just its endpoints.
+    pub path_name: Option<&'a str>,
+    /// `true` for `OPTIONAL MATCH`: a row survives even when this clause
+    /// fails to extend it, with every name it introduces set to null.
+    pub optional: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AggregateFunc {
+    Count,
+    Sum,
+    Min,
+    Max,
+    Collect,
+}
+
+/// One entry of a `RETURN` clause: a bare bound name, a property access
+/// on a bound name, or an aggregate over either. `Aggregate { arg: None, .. }`
+/// is `count(*)`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReturnItem<'a> {
+    Name(&'a str),
+    Property {
+        target: &'a str,
+        key: &'a str,
+    },
+    Aggregate {
+        func: AggregateFunc,
+        arg: Option<Box<ReturnItem<'a>>>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct Query<'a> {
+    pub match_clauses: Vec<MatchClause<'a>>,
+    pub where_clauses: Vec<Expr<'a>>,
+    pub return_clause: Vec<ReturnItem<'a>>,
+}
@@ -1,12 +1,20 @@
 use crate::parser::ast;
 use crate::Error;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use super::plan::{Filter, MatchStep, NamedValue, QueryPlan};
+use super::plan::{Filter, MatchStep, NamedValue, Projection, QueryPlan, Value};
 
 pub(crate) struct BuildEnv<'a> {
     names: HashMap<&'a str, NamedValue>,
     next_name: usize,
+    next_scope: usize,
+    /// Set while compiling an `OPTIONAL MATCH` clause; every slot
+    /// `next_name` hands out while it's set is recorded in `nullable`.
+    optional_scope: Option<usize>,
+    /// Slots first created inside an optional scope. A later mandatory
+    /// clause referencing one of these through `get_node`/`get_edge`
+    /// fails, since the name may not have been bound at all.
+    nullable: HashSet<usize>,
 }
 
 impl<'a> BuildEnv<'a> {
@@ -14,26 +22,52 @@ impl<'a> BuildEnv<'a> {
         Self {
             names: HashMap::new(),
             next_name: 0,
+            next_scope: 0,
+            optional_scope: None,
+            nullable: HashSet::new(),
         }
     }
 
     fn next_name(&mut self) -> usize {
+        let name = self.next_name;
         self.next_name += 1;
-        self.next_name - 1
+        if self.optional_scope.is_some() {
+            self.nullable.insert(name);
+        }
+        name
+    }
+
+    fn next_scope(&mut self) -> usize {
+        self.next_scope += 1;
+        self.next_scope - 1
+    }
+
+    fn is_nullable(&self, name: usize) -> bool {
+        self.nullable.contains(&name)
     }
 
     fn get_node(&self, name: &str) -> Result<Option<usize>, Error> {
         match self.names.get(&name) {
-            Some(NamedValue::Node(name)) => Ok(Some(*name)),
-            Some(NamedValue::Edge(_)) => Err(Error::Todo),
+            Some(NamedValue::Node(name)) => {
+                if self.optional_scope.is_none() && self.nullable.contains(name) {
+                    return Err(Error::Todo);
+                }
+                Ok(Some(*name))
+            }
+            Some(NamedValue::Edge(_)) | Some(NamedValue::Path(_)) => Err(Error::Todo),
             None => Ok(None),
         }
     }
 
     fn get_edge(&self, name: &str) -> Result<Option<usize>, Error> {
         match self.names.get(&name) {
-            Some(NamedValue::Node(_)) => Err(Error::Todo),
-            Some(NamedValue::Edge(name)) => Ok(Some(*name)),
+            Some(NamedValue::Node(_)) | Some(NamedValue::Path(_)) => Err(Error::Todo),
+            Some(NamedValue::Edge(name)) => {
+                if self.optional_scope.is_none() && self.nullable.contains(name) {
+                    return Err(Error::Todo);
+                }
+                Ok(Some(*name))
+            }
             None => Ok(None),
         }
     }
@@ -41,7 +75,7 @@ impl<'a> BuildEnv<'a> {
     fn create_node(&mut self, name: &'a str) -> Result<usize, Error> {
         match self.names.get(&name) {
             Some(NamedValue::Node(name)) => Ok(*name),
-            Some(NamedValue::Edge(_)) => Err(Error::Todo),
+            Some(NamedValue::Edge(_)) | Some(NamedValue::Path(_)) => Err(Error::Todo),
             None => {
                 let next_name = self.next_name();
                 self.names.insert(name, NamedValue::Node(next_name));
@@ -52,7 +86,7 @@ impl<'a> BuildEnv<'a> {
 
     fn create_edge(&mut self, name: &'a str) -> Result<usize, Error> {
         match self.names.get(&name) {
-            Some(NamedValue::Node(_)) => Err(Error::Todo),
+            Some(NamedValue::Node(_)) | Some(NamedValue::Path(_)) => Err(Error::Todo),
             Some(NamedValue::Edge(name)) => Ok(*name),
             None => {
                 let next_name = self.next_name();
@@ -61,90 +95,271 @@ impl<'a> BuildEnv<'a> {
             }
         }
     }
+
+    /// Resolves a name to its slot regardless of whether it was bound as
+    /// a node or an edge, for use by property filters that don't care
+    /// which kind they're matching against.
+    fn resolve_name(&self, name: &str) -> Result<usize, Error> {
+        match self.names.get(&name) {
+            Some(NamedValue::Node(name)) => Ok(*name),
+            Some(NamedValue::Edge(name)) => Ok(*name),
+            Some(NamedValue::Path(_)) | None => Err(Error::Todo),
+        }
+    }
+
+    fn get_path(&self, name: &str) -> Result<Option<usize>, Error> {
+        match self.names.get(&name) {
+            Some(NamedValue::Path(name)) => Ok(Some(*name)),
+            Some(NamedValue::Node(_)) | Some(NamedValue::Edge(_)) => Err(Error::Todo),
+            None => Ok(None),
+        }
+    }
+
+    fn create_path(&mut self, name: &'a str) -> Result<usize, Error> {
+        match self.names.get(&name) {
+            Some(NamedValue::Path(name)) => Ok(*name),
+            Some(NamedValue::Node(_)) | Some(NamedValue::Edge(_)) => Err(Error::Todo),
+            None => {
+                let next_name = self.next_name();
+                self.names.insert(name, NamedValue::Path(next_name));
+                Ok(next_name)
+            }
+        }
+    }
 }
 
-impl QueryPlan {
-    pub fn new(query: &ast::Query) -> Result<QueryPlan, Error> {
-        if query.match_clauses.is_empty() && !query.where_clauses.is_empty() {
-            return Err(Error::Todo);
+fn compile_literal(literal: &ast::Literal) -> Value {
+    match literal {
+        ast::Literal::String(value) => Value::String((*value).to_string()),
+        ast::Literal::Integer(value) => Value::Integer(*value),
+        ast::Literal::Float(value) => Value::Float(*value),
+        ast::Literal::Bool(value) => Value::Bool(*value),
+        ast::Literal::Null => Value::Null,
+    }
+}
+
+fn compile_expr(env: &BuildEnv, expr: &ast::Expr) -> Result<Filter, Error> {
+    match expr {
+        ast::Expr::PropertyEquals { target, key, value } => Ok(Filter::PropertyEquals {
+            target: env.resolve_name(target)?,
+            key: key.to_string(),
+            value: compile_literal(value),
+        }),
+        ast::Expr::PropertyCompare {
+            target,
+            key,
+            op,
+            value,
+        } => Ok(Filter::PropertyCompare {
+            target: env.resolve_name(target)?,
+            key: key.to_string(),
+            op: *op,
+            value: compile_literal(value),
+        }),
+        ast::Expr::PropertyExists { target, key } => Ok(Filter::PropertyExists {
+            target: env.resolve_name(target)?,
+            key: key.to_string(),
+        }),
+        ast::Expr::And(left, right) => Ok(Filter::and(
+            compile_expr(env, left)?,
+            compile_expr(env, right)?,
+        )),
+        ast::Expr::Or(left, right) => Ok(Filter::or(
+            compile_expr(env, left)?,
+            compile_expr(env, right)?,
+        )),
+    }
+}
+
+fn push_property_filters(
+    steps: &mut Vec<MatchStep>,
+    target: usize,
+    properties: &[(&str, ast::Literal)],
+) {
+    for (key, value) in properties {
+        steps.push(MatchStep::Filter(Filter::PropertyEquals {
+            target,
+            key: key.to_string(),
+            value: compile_literal(value),
+        }));
+    }
+}
+
+fn compile_projection(env: &BuildEnv, item: &ast::ReturnItem) -> Result<Projection, Error> {
+    match item {
+        ast::ReturnItem::Name(name) => {
+            let value = *env.names.get(name).ok_or(Error::Todo)?;
+            let slot = match value {
+                NamedValue::Node(name) | NamedValue::Edge(name) | NamedValue::Path(name) => name,
+            };
+            Ok(Projection::Bound {
+                value,
+                nullable: env.is_nullable(slot),
+            })
         }
-        if query.match_clauses.is_empty() && !query.return_clause.is_empty() {
-            return Err(Error::Todo);
+        ast::ReturnItem::Property { target, key } => {
+            let target = env.resolve_name(target)?;
+            Ok(Projection::Property {
+                target,
+                key: key.to_string(),
+                nullable: env.is_nullable(target),
+            })
         }
+        ast::ReturnItem::Aggregate { func, arg } => {
+            let arg = match arg {
+                Some(inner) => Some(Box::new(compile_projection(env, inner)?)),
+                None => None,
+            };
+            Ok(Projection::Aggregate { func: *func, arg })
+        }
+    }
+}
 
-        let mut env = BuildEnv::new();
-        let mut steps = vec![];
+impl QueryPlan {
+    pub fn new(query: &ast::Query) -> Result<QueryPlan, Error> {
+        Self::new_with_statistics(query, &super::optimize::DefaultStatistics)
+    }
 
-        for clause in &query.match_clauses {
-            let mut prev_node_name = if let Some(name) = clause.start.annotation.name {
-                if let Some(name) = env.get_node(name)? {
-                    name
-                } else {
-                    let name = env.create_node(name)?;
-                    steps.push(MatchStep::LoadAnyNode { name });
-                    name
-                }
+    pub fn new_with_statistics(
+        query: &ast::Query,
+        stats: &dyn super::optimize::Statistics,
+    ) -> Result<QueryPlan, Error> {
+        let (steps, returns) = build_steps(query)?;
+        let (steps, estimated_cost) = super::optimize::optimize(steps, stats);
+        Ok(QueryPlan {
+            steps,
+            returns,
+            estimated_cost,
+        })
+    }
+}
+
+fn build_steps(query: &ast::Query) -> Result<(Vec<MatchStep>, Vec<Projection>), Error> {
+    if query.match_clauses.is_empty() && !query.return_clause.is_empty() {
+        return Err(Error::Todo);
+    }
+
+    let mut env = BuildEnv::new();
+    let mut steps = vec![];
+
+    for clause in &query.match_clauses {
+        let mut path_elements = vec![];
+        let scope_start = env.next_name;
+
+        let scope = if clause.optional {
+            let scope = env.next_scope();
+            env.optional_scope = Some(scope);
+            steps.push(MatchStep::BeginOptional { scope });
+            Some(scope)
+        } else {
+            None
+        };
+
+        let mut prev_node_name = if let Some(name) = clause.start.annotation.name {
+            if let Some(name) = env.get_node(name)? {
+                name
             } else {
-                let name = env.next_name();
+                let name = env.create_node(name)?;
                 steps.push(MatchStep::LoadAnyNode { name });
                 name
-            };
-
-            if let Some(label) = clause.start.annotation.label {
-                steps.push(MatchStep::Filter(Filter::NodeHasLabel {
-                    node: prev_node_name,
-                    label: label.to_string(),
-                }));
             }
+        } else {
+            let name = env.next_name();
+            steps.push(MatchStep::LoadAnyNode { name });
+            name
+        };
 
-            for (edge, node) in &clause.edges {
-                let edge_name = if let Some(name) = edge.annotation.name {
-                    if let Some(name) = env.get_edge(name)? {
-                        match edge.direction {
-                            ast::Direction::Left => {
-                                steps.push(MatchStep::Filter(Filter::IsTarget {
-                                    node: prev_node_name,
-                                    edge: name,
-                                }))
-                            }
-                            ast::Direction::Right => {
-                                steps.push(MatchStep::Filter(Filter::IsOrigin {
-                                    node: prev_node_name,
-                                    edge: name,
-                                }))
-                            }
-                            ast::Direction::Either => steps.push(MatchStep::Filter(Filter::or(
-                                Filter::IsOrigin {
-                                    node: prev_node_name,
-                                    edge: name,
-                                },
-                                Filter::IsTarget {
-                                    node: prev_node_name,
-                                    edge: name,
-                                },
-                            ))),
-                        }
+        if let Some(label) = clause.start.annotation.label {
+            steps.push(MatchStep::Filter(Filter::NodeHasLabel {
+                node: prev_node_name,
+                label: label.to_string(),
+            }));
+        }
+        push_property_filters(
+            &mut steps,
+            prev_node_name,
+            clause.start.annotation.properties,
+        );
+        path_elements.push(NamedValue::Node(prev_node_name));
+
+        for (edge, node) in &clause.edges {
+            if let Some(hops) = edge.annotation.hops {
+                let node_name = if let Some(name) = node.annotation.name {
+                    if let Some(name) = env.get_node(name)? {
                         name
                     } else {
-                        let name = env.create_edge(name)?;
-                        match edge.direction {
-                            ast::Direction::Left => steps.push(MatchStep::LoadTargetEdge {
-                                name,
-                                node: prev_node_name,
-                            }),
-                            ast::Direction::Right => steps.push(MatchStep::LoadOriginEdge {
-                                name,
+                        env.create_node(name)?
+                    }
+                } else {
+                    env.next_name()
+                };
+
+                // `Path` is alternating node/edge, but a variable-length hop
+                // has no single bound edge to record — it's a whole stretch
+                // of them. Stand in a fresh, otherwise-unused slot as the
+                // edge element so the sequence still alternates; `length()`
+                // then counts the stretch as one relationship rather than
+                // silently dropping it, undercounting the true hop count
+                // for `min`/`max` greater than one. It's resolved by the
+                // `LoadVariableEdge` step itself rather than needing a
+                // dependency of its own.
+                let edge_placeholder = env.next_name();
+                path_elements.push(NamedValue::Edge(edge_placeholder));
+
+                steps.push(MatchStep::LoadVariableEdge {
+                    name: node_name,
+                    node: prev_node_name,
+                    min: hops.min,
+                    max: hops.max,
+                    direction: edge.direction,
+                    label: edge.annotation.label.map(|label| label.to_string()),
+                    properties: edge
+                        .annotation
+                        .properties
+                        .iter()
+                        .map(|(key, value)| (key.to_string(), compile_literal(value)))
+                        .collect(),
+                    path_edge: Some(edge_placeholder),
+                });
+
+                if let Some(label) = node.annotation.label {
+                    steps.push(MatchStep::Filter(Filter::NodeHasLabel {
+                        node: node_name,
+                        label: label.to_string(),
+                    }));
+                }
+                push_property_filters(&mut steps, node_name, node.annotation.properties);
+
+                prev_node_name = node_name;
+                path_elements.push(NamedValue::Node(prev_node_name));
+                continue;
+            }
+
+            let edge_name = if let Some(name) = edge.annotation.name {
+                if let Some(name) = env.get_edge(name)? {
+                    match edge.direction {
+                        ast::Direction::Left => steps.push(MatchStep::Filter(Filter::IsTarget {
+                            node: prev_node_name,
+                            edge: name,
+                        })),
+                        ast::Direction::Right => steps.push(MatchStep::Filter(Filter::IsOrigin {
+                            node: prev_node_name,
+                            edge: name,
+                        })),
+                        ast::Direction::Either => steps.push(MatchStep::Filter(Filter::or(
+                            Filter::IsOrigin {
                                 node: prev_node_name,
-                            }),
-                            ast::Direction::Either => steps.push(MatchStep::LoadEitherEdge {
-                                name,
+                                edge: name,
+                            },
+                            Filter::IsTarget {
                                 node: prev_node_name,
-                            }),
-                        }
-                        name
+                                edge: name,
+                            },
+                        ))),
                     }
+                    name
                 } else {
-                    let name = env.next_name();
+                    let name = env.create_edge(name)?;
                     match edge.direction {
                         ast::Direction::Left => steps.push(MatchStep::LoadTargetEdge {
                             name,
@@ -160,75 +375,72 @@ impl QueryPlan {
                         }),
                     }
                     name
-                };
-
-                if let Some(label) = edge.annotation.label {
-                    steps.push(MatchStep::Filter(Filter::EdgeHasLabel {
-                        edge: edge_name,
-                        label: label.to_string(),
-                    }));
                 }
+            } else {
+                let name = env.next_name();
+                match edge.direction {
+                    ast::Direction::Left => steps.push(MatchStep::LoadTargetEdge {
+                        name,
+                        node: prev_node_name,
+                    }),
+                    ast::Direction::Right => steps.push(MatchStep::LoadOriginEdge {
+                        name,
+                        node: prev_node_name,
+                    }),
+                    ast::Direction::Either => steps.push(MatchStep::LoadEitherEdge {
+                        name,
+                        node: prev_node_name,
+                    }),
+                }
+                name
+            };
 
-                prev_node_name = if let Some(name) = node.annotation.name {
-                    if let Some(name) = env.get_node(name)? {
-                        match edge.direction {
-                            ast::Direction::Left => {
-                                steps.push(MatchStep::Filter(Filter::IsOrigin {
+            if let Some(label) = edge.annotation.label {
+                steps.push(MatchStep::Filter(Filter::EdgeHasLabel {
+                    edge: edge_name,
+                    label: label.to_string(),
+                }));
+            }
+            push_property_filters(&mut steps, edge_name, edge.annotation.properties);
+            path_elements.push(NamedValue::Edge(edge_name));
+
+            prev_node_name = if let Some(name) = node.annotation.name {
+                if let Some(name) = env.get_node(name)? {
+                    match edge.direction {
+                        ast::Direction::Left => steps.push(MatchStep::Filter(Filter::IsOrigin {
+                            node: name,
+                            edge: edge_name,
+                        })),
+                        ast::Direction::Right => steps.push(MatchStep::Filter(Filter::IsTarget {
+                            node: name,
+                            edge: edge_name,
+                        })),
+                        ast::Direction::Either => steps.push(MatchStep::Filter(Filter::or(
+                            Filter::and(
+                                Filter::IsOrigin {
                                     node: name,
                                     edge: edge_name,
-                                }))
-                            }
-                            ast::Direction::Right => {
-                                steps.push(MatchStep::Filter(Filter::IsTarget {
+                                },
+                                Filter::IsTarget {
+                                    node: prev_node_name,
+                                    edge: edge_name,
+                                },
+                            ),
+                            Filter::and(
+                                Filter::IsTarget {
                                     node: name,
                                     edge: edge_name,
-                                }))
-                            }
-                            ast::Direction::Either => steps.push(MatchStep::Filter(Filter::or(
-                                Filter::and(
-                                    Filter::IsOrigin {
-                                        node: name,
-                                        edge: edge_name,
-                                    },
-                                    Filter::IsTarget {
-                                        node: prev_node_name,
-                                        edge: edge_name,
-                                    },
-                                ),
-                                Filter::and(
-                                    Filter::IsTarget {
-                                        node: name,
-                                        edge: edge_name,
-                                    },
-                                    Filter::IsOrigin {
-                                        node: prev_node_name,
-                                        edge: edge_name,
-                                    },
-                                ),
-                            ))),
-                        }
-                        name
-                    } else {
-                        let name = env.create_node(name)?;
-                        match edge.direction {
-                            ast::Direction::Left => steps.push(MatchStep::LoadOriginNode {
-                                name,
-                                edge: edge_name,
-                            }),
-                            ast::Direction::Right => steps.push(MatchStep::LoadTargetNode {
-                                name,
-                                edge: edge_name,
-                            }),
-                            ast::Direction::Either => steps.push(MatchStep::LoadOtherNode {
-                                name,
-                                node: prev_node_name,
-                                edge: edge_name,
-                            }),
-                        }
-                        name
+                                },
+                                Filter::IsOrigin {
+                                    node: prev_node_name,
+                                    edge: edge_name,
+                                },
+                            ),
+                        ))),
                     }
+                    name
                 } else {
-                    let name = env.next_name();
+                    let name = env.create_node(name)?;
                     match edge.direction {
                         ast::Direction::Left => steps.push(MatchStep::LoadOriginNode {
                             name,
@@ -245,22 +457,413 @@ impl QueryPlan {
                         }),
                     }
                     name
-                };
-
-                if let Some(label) = node.annotation.label {
-                    steps.push(MatchStep::Filter(Filter::NodeHasLabel {
+                }
+            } else {
+                let name = env.next_name();
+                match edge.direction {
+                    ast::Direction::Left => steps.push(MatchStep::LoadOriginNode {
+                        name,
+                        edge: edge_name,
+                    }),
+                    ast::Direction::Right => steps.push(MatchStep::LoadTargetNode {
+                        name,
+                        edge: edge_name,
+                    }),
+                    ast::Direction::Either => steps.push(MatchStep::LoadOtherNode {
+                        name,
                         node: prev_node_name,
-                        label: label.to_string(),
-                    }));
+                        edge: edge_name,
+                    }),
                 }
+                name
+            };
+
+            if let Some(label) = node.annotation.label {
+                steps.push(MatchStep::Filter(Filter::NodeHasLabel {
+                    node: prev_node_name,
+                    label: label.to_string(),
+                }));
             }
+            push_property_filters(&mut steps, prev_node_name, node.annotation.properties);
+            path_elements.push(NamedValue::Node(prev_node_name));
+        }
+
+        if let Some(path_name) = clause.path_name {
+            let name = if let Some(name) = env.get_path(path_name)? {
+                name
+            } else {
+                env.create_path(path_name)?
+            };
+            steps.push(MatchStep::BindPath {
+                name,
+                elements: path_elements,
+            });
         }
 
-        let mut returns = Vec::with_capacity(query.return_clause.len());
-        for &name in &query.return_clause {
-            returns.push(*env.names.get(name).ok_or(Error::Todo)?);
+        if let Some(scope) = scope {
+            env.optional_scope = None;
+            let bound_names = env
+                .names
+                .values()
+                .filter(|value| {
+                    let slot = match value {
+                        NamedValue::Node(name)
+                        | NamedValue::Edge(name)
+                        | NamedValue::Path(name) => *name,
+                    };
+                    slot >= scope_start
+                })
+                .copied()
+                .collect();
+            steps.push(MatchStep::EndOptional { scope, bound_names });
         }
+    }
+
+    for expr in &query.where_clauses {
+        steps.push(MatchStep::Filter(compile_expr(&env, expr)?));
+    }
+
+    let mut returns = Vec::with_capacity(query.return_clause.len());
+    for item in &query.return_clause {
+        returns.push(compile_projection(&env, item)?);
+    }
+
+    if returns
+        .iter()
+        .any(|projection| matches!(projection, Projection::Aggregate { .. }))
+    {
+        let (aggregates, group_by): (Vec<Projection>, Vec<Projection>) = returns
+            .iter()
+            .cloned()
+            .partition(|projection| matches!(projection, Projection::Aggregate { .. }));
+        steps.push(MatchStep::Aggregate {
+            group_by,
+            aggregates,
+        });
+    }
+
+    Ok((steps, returns))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ast::{
+        AggregateFunc, CompareOp, Direction, EdgeAnnotation, EdgeRef, Expr, HopRange, Literal,
+        MatchClause, NodeAnnotation, NodeRef, Query, ReturnItem,
+    };
+    use crate::planner::optimize::DefaultStatistics;
+    use crate::planner::plan::Path;
+
+    const NO_PROPERTIES: &[(&str, Literal)] = &[];
+
+    fn empty_node<'a>(name: Option<&'a str>) -> NodeRef<'a> {
+        NodeRef {
+            annotation: NodeAnnotation {
+                name,
+                label: None,
+                properties: NO_PROPERTIES,
+            },
+        }
+    }
+
+    fn plan(query: &Query) -> QueryPlan {
+        QueryPlan::new_with_statistics(query, &DefaultStatistics).expect("plan should compile")
+    }
+
+    #[test]
+    fn inline_property_map_compiles_to_a_property_filter() {
+        let properties = [("name", Literal::String("Alice"))];
+        let query = Query {
+            match_clauses: vec![MatchClause {
+                start: NodeRef {
+                    annotation: NodeAnnotation {
+                        name: Some("a"),
+                        label: None,
+                        properties: &properties,
+                    },
+                },
+                edges: vec![],
+                path_name: None,
+                optional: false,
+            }],
+            where_clauses: vec![],
+            return_clause: vec![ReturnItem::Name("a")],
+        };
+
+        let plan = plan(&query);
+        assert!(plan.steps.iter().any(|step| matches!(
+            step,
+            MatchStep::Filter(Filter::PropertyEquals { target: 0, key, value: Value::String(v) })
+                if key == "name" && v == "Alice"
+        )));
+    }
+
+    #[test]
+    fn where_clause_compiles_to_a_property_compare_filter() {
+        let query = Query {
+            match_clauses: vec![MatchClause {
+                start: empty_node(Some("a")),
+                edges: vec![],
+                path_name: None,
+                optional: false,
+            }],
+            where_clauses: vec![Expr::PropertyCompare {
+                target: "a",
+                key: "age",
+                op: CompareOp::Gt,
+                value: Literal::Integer(30),
+            }],
+            return_clause: vec![ReturnItem::Name("a")],
+        };
+
+        let plan = plan(&query);
+        assert!(plan.steps.iter().any(|step| matches!(
+            step,
+            MatchStep::Filter(Filter::PropertyCompare {
+                target: 0,
+                key,
+                op: CompareOp::Gt,
+                value: Value::Integer(30),
+            }) if key == "age"
+        )));
+    }
+
+    #[test]
+    fn property_return_item_compiles_to_a_property_projection() {
+        let query = Query {
+            match_clauses: vec![MatchClause {
+                start: empty_node(Some("a")),
+                edges: vec![],
+                path_name: None,
+                optional: false,
+            }],
+            where_clauses: vec![],
+            return_clause: vec![ReturnItem::Property {
+                target: "a",
+                key: "name",
+            }],
+        };
+
+        let plan = plan(&query);
+        assert_eq!(
+            plan.returns,
+            vec![Projection::Property {
+                target: 0,
+                key: "name".to_string(),
+                nullable: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn aggregate_return_item_emits_an_aggregate_step_and_projection() {
+        let query = Query {
+            match_clauses: vec![MatchClause {
+                start: empty_node(Some("a")),
+                edges: vec![],
+                path_name: None,
+                optional: false,
+            }],
+            where_clauses: vec![],
+            return_clause: vec![ReturnItem::Aggregate {
+                func: AggregateFunc::Count,
+                arg: None,
+            }],
+        };
+
+        let plan = plan(&query);
+        assert_eq!(
+            plan.returns,
+            vec![Projection::Aggregate {
+                func: AggregateFunc::Count,
+                arg: None,
+            }]
+        );
+        assert!(plan.steps.iter().any(|step| matches!(
+            step,
+            MatchStep::Aggregate { group_by, aggregates }
+                if group_by.is_empty() && aggregates.len() == 1
+        )));
+    }
+
+    #[test]
+    fn variable_length_hop_carries_its_edge_label() {
+        let no_properties: &[(&str, Literal)] = &[];
+        let query = Query {
+            match_clauses: vec![MatchClause {
+                start: empty_node(Some("a")),
+                edges: vec![(
+                    EdgeRef {
+                        annotation: EdgeAnnotation {
+                            name: None,
+                            label: Some("KNOWS"),
+                            properties: no_properties,
+                            hops: Some(HopRange {
+                                min: 1,
+                                max: Some(3),
+                            }),
+                        },
+                        direction: Direction::Right,
+                    },
+                    empty_node(Some("b")),
+                )],
+                path_name: Some("p"),
+                optional: false,
+            }],
+            where_clauses: vec![],
+            return_clause: vec![ReturnItem::Name("p")],
+        };
+
+        let plan = plan(&query);
+        assert!(plan.steps.iter().any(|step| matches!(
+            step,
+            MatchStep::LoadVariableEdge { label: Some(l), min: 1, max: Some(3), .. } if l == "KNOWS"
+        )));
+    }
+
+    #[test]
+    fn variable_length_hop_endpoint_keeps_its_property_map() {
+        let no_properties: &[(&str, Literal)] = &[];
+        let endpoint_properties = [("age", Literal::Integer(30))];
+        let query = Query {
+            match_clauses: vec![MatchClause {
+                start: empty_node(Some("a")),
+                edges: vec![(
+                    EdgeRef {
+                        annotation: EdgeAnnotation {
+                            name: None,
+                            label: Some("KNOWS"),
+                            properties: no_properties,
+                            hops: Some(HopRange {
+                                min: 1,
+                                max: Some(3),
+                            }),
+                        },
+                        direction: Direction::Right,
+                    },
+                    NodeRef {
+                        annotation: NodeAnnotation {
+                            name: Some("b"),
+                            label: None,
+                            properties: &endpoint_properties,
+                        },
+                    },
+                )],
+                path_name: None,
+                optional: false,
+            }],
+            where_clauses: vec![],
+            return_clause: vec![ReturnItem::Name("b")],
+        };
+
+        let plan = plan(&query);
+        assert!(plan.steps.iter().any(|step| matches!(
+            step,
+            MatchStep::Filter(Filter::PropertyEquals { target: 1, key, value: Value::Integer(30) })
+                if key == "age"
+        )));
+    }
+
+    #[test]
+    fn bound_path_alternates_across_a_variable_length_hop() {
+        let no_properties: &[(&str, Literal)] = &[];
+        let query = Query {
+            match_clauses: vec![MatchClause {
+                start: empty_node(Some("a")),
+                edges: vec![(
+                    EdgeRef {
+                        annotation: EdgeAnnotation {
+                            name: None,
+                            label: None,
+                            properties: no_properties,
+                            hops: Some(HopRange { min: 1, max: None }),
+                        },
+                        direction: Direction::Right,
+                    },
+                    empty_node(Some("b")),
+                )],
+                path_name: Some("p"),
+                optional: false,
+            }],
+            where_clauses: vec![],
+            return_clause: vec![ReturnItem::Name("p")],
+        };
+
+        let plan = plan(&query);
+        let elements = plan
+            .steps
+            .iter()
+            .find_map(|step| match step {
+                MatchStep::BindPath { elements, .. } => Some(elements.clone()),
+                _ => None,
+            })
+            .expect("a BindPath step should be emitted");
+
+        assert!(matches!(elements[0], NamedValue::Node(_)));
+        assert!(matches!(elements[1], NamedValue::Edge(_)));
+        assert!(matches!(elements[2], NamedValue::Node(_)));
+
+        let path = Path::new(elements);
+        assert_eq!(path.length(), 1);
+        assert_eq!(path.nodes().count(), 2);
+    }
+
+    #[test]
+    fn optional_match_marks_its_new_bindings_nullable() {
+        let no_properties: &[(&str, Literal)] = &[];
+        let query = Query {
+            match_clauses: vec![
+                MatchClause {
+                    start: empty_node(Some("a")),
+                    edges: vec![],
+                    path_name: None,
+                    optional: false,
+                },
+                MatchClause {
+                    start: empty_node(Some("a")),
+                    edges: vec![(
+                        EdgeRef {
+                            annotation: EdgeAnnotation {
+                                name: None,
+                                label: Some("KNOWS"),
+                                properties: no_properties,
+                                hops: None,
+                            },
+                            direction: Direction::Right,
+                        },
+                        empty_node(Some("b")),
+                    )],
+                    path_name: None,
+                    optional: true,
+                },
+            ],
+            where_clauses: vec![],
+            return_clause: vec![ReturnItem::Name("a"), ReturnItem::Name("b")],
+        };
 
-        Ok(QueryPlan { steps, returns })
+        let plan = plan(&query);
+        assert_eq!(
+            plan.returns,
+            vec![
+                Projection::Bound {
+                    value: NamedValue::Node(0),
+                    nullable: false,
+                },
+                Projection::Bound {
+                    value: NamedValue::Node(2),
+                    nullable: true,
+                },
+            ]
+        );
+        assert!(plan
+            .steps
+            .iter()
+            .any(|step| matches!(step, MatchStep::BeginOptional { scope: 0 })));
+        assert!(plan.steps.iter().any(|step| matches!(
+            step,
+            MatchStep::EndOptional { scope: 0, bound_names }
+                if bound_names == &[NamedValue::Node(2)]
+        )));
     }
 }
@@ -0,0 +1,3 @@
+mod build;
+pub mod optimize;
+pub mod plan;
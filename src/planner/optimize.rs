@@ -0,0 +1,501 @@
+use std::collections::{HashMap, HashSet};
+
+use super::plan::{Filter, MatchStep, Projection};
+
+/// Cardinality estimates the storage layer supplies to the planner so it
+/// can pick a cheaper starting point and expansion order than the
+/// textual order of the pattern.
+pub trait Statistics {
+    /// Estimated number of nodes carrying `label`.
+    fn node_count(&self, label: &str) -> usize;
+    /// Estimated average number of outgoing/incoming edges per node for
+    /// `label`.
+    fn edge_fan_out(&self, label: &str) -> f64;
+}
+
+/// Used when the caller has no real statistics: every label is assumed to
+/// match `usize::MAX` nodes, which still ranks below an unconstrained scan
+/// (`f64::MAX`), so any labeled step wins against one, and a single-edge
+/// fan-out is assumed for edge expansion.
+pub(crate) struct DefaultStatistics;
+
+impl Statistics for DefaultStatistics {
+    fn node_count(&self, _label: &str) -> usize {
+        usize::MAX
+    }
+
+    fn edge_fan_out(&self, _label: &str) -> f64 {
+        1.0
+    }
+}
+
+fn produces(step: &MatchStep) -> Option<usize> {
+    match step {
+        MatchStep::LoadAnyNode { name }
+        | MatchStep::LoadOriginEdge { name, .. }
+        | MatchStep::LoadTargetEdge { name, .. }
+        | MatchStep::LoadEitherEdge { name, .. }
+        | MatchStep::LoadOriginNode { name, .. }
+        | MatchStep::LoadTargetNode { name, .. }
+        | MatchStep::LoadOtherNode { name, .. }
+        | MatchStep::LoadVariableEdge { name, .. }
+        | MatchStep::BindPath { name, .. } => Some(*name),
+        MatchStep::Filter(_)
+        | MatchStep::BeginOptional { .. }
+        | MatchStep::EndOptional { .. }
+        | MatchStep::Aggregate { .. } => None,
+    }
+}
+
+/// Slots a step resolves beyond the primary name `produces()` reports.
+/// `LoadVariableEdge`'s `path_edge` placeholder isn't bound by any step of
+/// its own — it's resolved the moment the step that introduces it runs —
+/// so without this, a `BindPath` depending on it could never be scheduled
+/// on its merits and would always fall through to the dependency-cycle
+/// fallback.
+fn produces_extra(step: &MatchStep) -> Option<usize> {
+    match step {
+        MatchStep::LoadVariableEdge { path_edge, .. } => *path_edge,
+        _ => None,
+    }
+}
+
+fn all_produces(step: &MatchStep) -> impl Iterator<Item = usize> {
+    produces(step).into_iter().chain(produces_extra(step))
+}
+
+fn filter_depends(filter: &Filter, out: &mut HashSet<usize>) {
+    match filter {
+        Filter::NodeHasLabel { node, .. } => {
+            out.insert(*node);
+        }
+        Filter::EdgeHasLabel { edge, .. } => {
+            out.insert(*edge);
+        }
+        Filter::IsOrigin { node, edge } | Filter::IsTarget { node, edge } => {
+            out.insert(*node);
+            out.insert(*edge);
+        }
+        Filter::PropertyEquals { target, .. }
+        | Filter::PropertyCompare { target, .. }
+        | Filter::PropertyExists { target, .. } => {
+            out.insert(*target);
+        }
+        Filter::And(left, right) | Filter::Or(left, right) => {
+            filter_depends(left, out);
+            filter_depends(right, out);
+        }
+    }
+}
+
+fn projection_depends(projection: &Projection, out: &mut HashSet<usize>) {
+    match projection {
+        Projection::Bound { value, .. } => {
+            out.insert(value.slot());
+        }
+        Projection::Property { target, .. } => {
+            out.insert(*target);
+        }
+        Projection::Aggregate { arg, .. } => {
+            if let Some(arg) = arg {
+                projection_depends(arg, out);
+            }
+        }
+    }
+}
+
+/// Names a step must already be bound before it can run.
+fn depends(step: &MatchStep) -> HashSet<usize> {
+    let mut out = HashSet::new();
+    match step {
+        MatchStep::LoadAnyNode { .. } | MatchStep::BeginOptional { .. } => {}
+        MatchStep::LoadOriginEdge { node, .. }
+        | MatchStep::LoadTargetEdge { node, .. }
+        | MatchStep::LoadEitherEdge { node, .. }
+        | MatchStep::LoadVariableEdge { node, .. } => {
+            out.insert(*node);
+        }
+        MatchStep::LoadOriginNode { edge, .. } | MatchStep::LoadTargetNode { edge, .. } => {
+            out.insert(*edge);
+        }
+        MatchStep::LoadOtherNode { node, edge, .. } => {
+            out.insert(*node);
+            out.insert(*edge);
+        }
+        MatchStep::Filter(filter) => filter_depends(filter, &mut out),
+        MatchStep::EndOptional { bound_names, .. } => {
+            for name in bound_names {
+                out.insert(name.slot());
+            }
+        }
+        MatchStep::BindPath { elements, .. } => {
+            for element in elements {
+                out.insert(element.slot());
+            }
+        }
+        MatchStep::Aggregate {
+            group_by,
+            aggregates,
+        } => {
+            for projection in group_by.iter().chain(aggregates) {
+                projection_depends(projection, &mut out);
+            }
+        }
+    }
+    out
+}
+
+/// Cost assumed for a node/edge pinned down by a `PropertyEquals` filter
+/// when no label statistics are available for it. There's no per-property
+/// cardinality estimate to draw on, but an equality filter is still a
+/// real constraint, so it must rank below an unconstrained scan
+/// (`f64::MAX`) rather than tying with one.
+const PROPERTY_EQUALS_COST: f64 = 1.0;
+
+fn collect_labels(
+    filter: &Filter,
+    node_label: &mut HashMap<usize, String>,
+    edge_label: &mut HashMap<usize, String>,
+    equality_filtered: &mut HashSet<usize>,
+) {
+    match filter {
+        Filter::NodeHasLabel { node, label } => {
+            node_label.entry(*node).or_insert_with(|| label.clone());
+        }
+        Filter::EdgeHasLabel { edge, label } => {
+            edge_label.entry(*edge).or_insert_with(|| label.clone());
+        }
+        Filter::PropertyEquals { target, .. } => {
+            equality_filtered.insert(*target);
+        }
+        Filter::And(left, right) | Filter::Or(left, right) => {
+            collect_labels(left, node_label, edge_label, equality_filtered);
+            collect_labels(right, node_label, edge_label, equality_filtered);
+        }
+        _ => {}
+    }
+}
+
+fn cost(
+    step: &MatchStep,
+    node_label: &HashMap<usize, String>,
+    edge_label: &HashMap<usize, String>,
+    equality_filtered: &HashSet<usize>,
+    stats: &dyn Statistics,
+) -> f64 {
+    match step {
+        MatchStep::LoadAnyNode { name } => {
+            if let Some(label) = node_label.get(name) {
+                stats.node_count(label) as f64
+            } else if equality_filtered.contains(name) {
+                PROPERTY_EQUALS_COST
+            } else {
+                f64::MAX
+            }
+        }
+        MatchStep::LoadOriginEdge { name, .. }
+        | MatchStep::LoadTargetEdge { name, .. }
+        | MatchStep::LoadEitherEdge { name, .. } => {
+            if let Some(label) = edge_label.get(name) {
+                stats.edge_fan_out(label)
+            } else if equality_filtered.contains(name) {
+                PROPERTY_EQUALS_COST
+            } else {
+                1.0
+            }
+        }
+        MatchStep::LoadVariableEdge { .. } => 2.0,
+        MatchStep::LoadOriginNode { .. }
+        | MatchStep::LoadTargetNode { .. }
+        | MatchStep::LoadOtherNode { .. } => 1.0,
+        MatchStep::Filter(_)
+        | MatchStep::BindPath { .. }
+        | MatchStep::Aggregate { .. }
+        | MatchStep::BeginOptional { .. }
+        | MatchStep::EndOptional { .. } => 0.0,
+    }
+}
+
+/// Reorders one straight-line run of steps (no optional scope inside it)
+/// into a cheaper, dependency-respecting order: a step only becomes a
+/// candidate once every name it depends on is bound, and among
+/// candidates the cheapest estimated one runs next. `initial_bound`
+/// seeds names already resolved by steps before this segment (e.g. by an
+/// earlier segment, or inside a preceding optional scope), so a step
+/// here depending on one of them is schedulable right away instead of
+/// hitting the dependency-cycle fallback below.
+fn optimize_segment(
+    segment: Vec<MatchStep>,
+    initial_bound: &HashSet<usize>,
+    stats: &dyn Statistics,
+) -> (Vec<MatchStep>, f64) {
+    if segment.is_empty() {
+        return (segment, 0.0);
+    }
+
+    let mut node_label = HashMap::new();
+    let mut edge_label = HashMap::new();
+    let mut equality_filtered = HashSet::new();
+    for step in &segment {
+        if let MatchStep::Filter(filter) = step {
+            collect_labels(
+                filter,
+                &mut node_label,
+                &mut edge_label,
+                &mut equality_filtered,
+            );
+        }
+    }
+
+    let depends: Vec<HashSet<usize>> = segment.iter().map(depends).collect();
+    let produces: Vec<Vec<usize>> = segment
+        .iter()
+        .map(|step| all_produces(step).collect())
+        .collect();
+    let costs: Vec<f64> = segment
+        .iter()
+        .map(|step| cost(step, &node_label, &edge_label, &equality_filtered, stats))
+        .collect();
+
+    let mut scheduled = vec![false; segment.len()];
+    let mut bound: HashSet<usize> = initial_bound.clone();
+    let mut order = Vec::with_capacity(segment.len());
+    let mut total_cost = 0.0;
+
+    while order.len() < segment.len() {
+        // `Statistics` is an external extension point, so a cost can be
+        // NaN (e.g. a fan-out computed as a 0/0 ratio); total_cmp orders
+        // those consistently instead of panicking like partial_cmp would.
+        let ready = (0..segment.len())
+            .filter(|&i| !scheduled[i] && depends[i].is_subset(&bound))
+            .min_by(|&a, &b| costs[a].total_cmp(&costs[b]));
+
+        // A builder bug producing a dependency cycle shouldn't hang the
+        // optimizer; fall back to the next unscheduled step in textual
+        // order instead of stalling.
+        let next = ready.unwrap_or_else(|| (0..segment.len()).find(|&i| !scheduled[i]).unwrap());
+
+        scheduled[next] = true;
+        for name in &produces[next] {
+            bound.insert(*name);
+        }
+        total_cost += costs[next];
+        order.push(next);
+    }
+
+    let mut segment: Vec<Option<MatchStep>> = segment.into_iter().map(Some).collect();
+    let steps = order
+        .into_iter()
+        .map(|i| segment[i].take().unwrap())
+        .collect();
+    (steps, total_cost)
+}
+
+/// Reorders the match steps the builder produced, preferring cheap,
+/// selective starting points and expansion order while preserving
+/// dependency correctness. Optional scopes (`BeginOptional`..`EndOptional`)
+/// run as an inner sub-search, so their contents are left in the
+/// builder's order rather than reordered against the outer plan. Names
+/// resolved anywhere before a segment — by an earlier segment or inside
+/// a preceding optional scope — are carried forward in `bound` so a
+/// later mandatory step referencing one (a further match clause, a
+/// `WHERE`, or an aggregate over an earlier binding) sees it as already
+/// satisfied rather than unresolved.
+pub(crate) fn optimize(steps: Vec<MatchStep>, stats: &dyn Statistics) -> (Vec<MatchStep>, f64) {
+    let mut out = Vec::with_capacity(steps.len());
+    let mut total_cost = 0.0;
+    let mut segment = Vec::new();
+    let mut in_optional = false;
+    let mut bound: HashSet<usize> = HashSet::new();
+
+    for step in steps {
+        match &step {
+            MatchStep::BeginOptional { .. } => {
+                let (ordered, cost) = optimize_segment(std::mem::take(&mut segment), &bound, stats);
+                for ordered_step in &ordered {
+                    bound.extend(all_produces(ordered_step));
+                }
+                out.extend(ordered);
+                total_cost += cost;
+                in_optional = true;
+                out.push(step);
+            }
+            MatchStep::EndOptional { .. } => {
+                out.push(step);
+                in_optional = false;
+            }
+            // Terminal: it folds every row the match produces, so it runs
+            // after all other steps instead of being scheduled on cost.
+            MatchStep::Aggregate { .. } => {
+                let (ordered, cost) = optimize_segment(std::mem::take(&mut segment), &bound, stats);
+                out.extend(ordered);
+                total_cost += cost;
+                out.push(step);
+            }
+            _ if in_optional => {
+                bound.extend(all_produces(&step));
+                out.push(step);
+            }
+            _ => segment.push(step),
+        }
+    }
+
+    let (ordered, cost) = optimize_segment(segment, &bound, stats);
+    out.extend(ordered);
+    total_cost += cost;
+
+    (out, total_cost)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefers_a_label_filtered_anchor_over_an_unconstrained_scan() {
+        struct Stats;
+        impl Statistics for Stats {
+            fn node_count(&self, label: &str) -> usize {
+                if label == "Person" {
+                    5
+                } else {
+                    usize::MAX
+                }
+            }
+            fn edge_fan_out(&self, _label: &str) -> f64 {
+                1.0
+            }
+        }
+
+        let steps = vec![
+            MatchStep::LoadAnyNode { name: 1 },
+            MatchStep::Filter(Filter::NodeHasLabel {
+                node: 0,
+                label: "Person".to_string(),
+            }),
+            MatchStep::LoadAnyNode { name: 0 },
+        ];
+
+        let (ordered, _) = optimize(steps, &Stats);
+        assert_eq!(ordered[0], MatchStep::LoadAnyNode { name: 0 });
+    }
+
+    #[test]
+    fn credits_a_property_equality_anchor_over_an_unconstrained_scan() {
+        let steps = vec![
+            MatchStep::LoadAnyNode { name: 1 },
+            MatchStep::Filter(Filter::PropertyEquals {
+                target: 0,
+                key: "name".to_string(),
+                value: super::super::plan::Value::String("Alice".to_string()),
+            }),
+            MatchStep::LoadAnyNode { name: 0 },
+        ];
+
+        let (ordered, _) = optimize(steps, &DefaultStatistics);
+        assert_eq!(ordered[0], MatchStep::LoadAnyNode { name: 0 });
+    }
+
+    #[test]
+    fn threads_bound_names_across_an_optional_scope() {
+        // `a` is bound before the optional scope; the segment after
+        // `EndOptional` has two independent steps, one depending on `a`
+        // (cheap: a plain edge load) and one depending on nothing (an
+        // unconstrained scan, maximally expensive). Without carrying
+        // `bound` across segments, the step depending on `a` could never
+        // be scheduled on cost: the scan would run first, and the edge
+        // load only afterwards through the textual-order fallback.
+        let steps = vec![
+            MatchStep::LoadAnyNode { name: 0 },
+            MatchStep::BeginOptional { scope: 0 },
+            MatchStep::LoadOriginEdge { name: 1, node: 0 },
+            MatchStep::EndOptional {
+                scope: 0,
+                bound_names: vec![super::super::plan::NamedValue::Edge(1)],
+            },
+            MatchStep::LoadAnyNode { name: 3 },
+            MatchStep::LoadOriginEdge { name: 2, node: 0 },
+        ];
+
+        let (ordered, _) = optimize(steps, &DefaultStatistics);
+        let edge_pos = ordered
+            .iter()
+            .position(|step| matches!(step, MatchStep::LoadOriginEdge { name: 2, .. }))
+            .unwrap();
+        let scan_pos = ordered
+            .iter()
+            .position(|step| matches!(step, MatchStep::LoadAnyNode { name: 3 }))
+            .unwrap();
+        assert!(edge_pos < scan_pos);
+    }
+
+    #[test]
+    fn nan_cost_does_not_panic_the_scheduler() {
+        struct NanStats;
+        impl Statistics for NanStats {
+            fn node_count(&self, _label: &str) -> usize {
+                usize::MAX
+            }
+            fn edge_fan_out(&self, _label: &str) -> f64 {
+                f64::NAN
+            }
+        }
+
+        let steps = vec![
+            MatchStep::LoadAnyNode { name: 0 },
+            MatchStep::LoadOriginEdge { name: 1, node: 0 },
+            MatchStep::Filter(Filter::EdgeHasLabel {
+                edge: 1,
+                label: "KNOWS".to_string(),
+            }),
+        ];
+
+        let (ordered, _) = optimize(steps, &NanStats);
+        assert_eq!(ordered.len(), 3);
+    }
+
+    #[test]
+    fn bind_path_schedules_on_its_variable_length_placeholder_dependency() {
+        use super::super::plan::NamedValue;
+
+        // `BindPath` depends on the `path_edge` placeholder `LoadVariableEdge`
+        // introduces; an independent, maximally expensive scan is also
+        // ready from the start. If the placeholder were never resolved by
+        // anything, `BindPath` could only ever run via the dependency-cycle
+        // fallback, after the expensive scan. With a real producer it is
+        // scheduled on its merits right after the steps it depends on.
+        let steps = vec![
+            MatchStep::LoadAnyNode { name: 0 },
+            MatchStep::LoadVariableEdge {
+                name: 1,
+                node: 0,
+                min: 1,
+                max: Some(3),
+                direction: crate::parser::ast::Direction::Right,
+                label: None,
+                properties: vec![],
+                path_edge: Some(2),
+            },
+            MatchStep::LoadAnyNode { name: 3 },
+            MatchStep::BindPath {
+                name: 4,
+                elements: vec![
+                    NamedValue::Node(0),
+                    NamedValue::Edge(2),
+                    NamedValue::Node(1),
+                ],
+            },
+        ];
+
+        let (ordered, _) = optimize(steps, &DefaultStatistics);
+        let bind_path_pos = ordered
+            .iter()
+            .position(|step| matches!(step, MatchStep::BindPath { .. }))
+            .unwrap();
+        let scan_pos = ordered
+            .iter()
+            .position(|step| matches!(step, MatchStep::LoadAnyNode { name: 3 }))
+            .unwrap();
+        assert!(bind_path_pos < scan_pos);
+    }
+}
@@ -0,0 +1,244 @@
+use std::fmt;
+
+use crate::parser::ast::{AggregateFunc, CompareOp, Direction};
+
+/// A literal value compared against a stored property, mirroring the
+/// value representation entries are stored with.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Bool(bool),
+    Null,
+}
+
+/// A name bound during matching: a node, an edge, or a path, identified by
+/// the `usize` slot `BuildEnv` assigned it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NamedValue {
+    Node(usize),
+    Edge(usize),
+    Path(usize),
+}
+
+impl NamedValue {
+    /// The `BuildEnv`-assigned slot underlying this binding, regardless
+    /// of whether it's a node, an edge, or a path.
+    pub fn slot(&self) -> usize {
+        match self {
+            NamedValue::Node(name) | NamedValue::Edge(name) | NamedValue::Path(name) => *name,
+        }
+    }
+}
+
+/// The ordered node/edge sequence bound by `p = (a)-[:R*]->(b)`, alternating
+/// starting and ending on a node. A variable-length hop (`*min..max`) has no
+/// single edge to bind, so its stretch is represented by one placeholder
+/// edge element rather than one per traversed edge; `length()` therefore
+/// counts such a stretch as a single relationship, a lower bound on the
+/// actual number of edges crossed rather than an exact count.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Path {
+    elements: Vec<NamedValue>,
+}
+
+impl Path {
+    pub fn new(elements: Vec<NamedValue>) -> Self {
+        Self { elements }
+    }
+
+    pub fn length(&self) -> usize {
+        self.relationships().count()
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = usize> + '_ {
+        self.elements.iter().filter_map(|element| match element {
+            NamedValue::Node(name) => Some(*name),
+            _ => None,
+        })
+    }
+
+    pub fn relationships(&self) -> impl Iterator<Item = usize> + '_ {
+        self.elements.iter().filter_map(|element| match element {
+            NamedValue::Edge(name) => Some(*name),
+            _ => None,
+        })
+    }
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rendered: Vec<String> = self
+            .elements
+            .iter()
+            .map(|element| match element {
+                NamedValue::Node(name) => name.to_string(),
+                NamedValue::Edge(name) => format!("r{name}"),
+                NamedValue::Path(name) => format!("p{name}"),
+            })
+            .collect();
+        write!(f, "{}", rendered.join("/"))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    NodeHasLabel {
+        node: usize,
+        label: String,
+    },
+    EdgeHasLabel {
+        edge: usize,
+        label: String,
+    },
+    IsOrigin {
+        node: usize,
+        edge: usize,
+    },
+    IsTarget {
+        node: usize,
+        edge: usize,
+    },
+    /// A missing key evaluates to non-match rather than an error, same as
+    /// a present key holding `Value::Null`.
+    PropertyEquals {
+        target: usize,
+        key: String,
+        value: Value,
+    },
+    PropertyCompare {
+        target: usize,
+        key: String,
+        op: CompareOp,
+        value: Value,
+    },
+    PropertyExists {
+        target: usize,
+        key: String,
+    },
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+}
+
+impl Filter {
+    pub fn and(left: Filter, right: Filter) -> Filter {
+        Filter::And(Box::new(left), Box::new(right))
+    }
+
+    pub fn or(left: Filter, right: Filter) -> Filter {
+        Filter::Or(Box::new(left), Box::new(right))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MatchStep {
+    LoadAnyNode {
+        name: usize,
+    },
+    LoadOriginEdge {
+        name: usize,
+        node: usize,
+    },
+    LoadTargetEdge {
+        name: usize,
+        node: usize,
+    },
+    LoadEitherEdge {
+        name: usize,
+        node: usize,
+    },
+    LoadOriginNode {
+        name: usize,
+        edge: usize,
+    },
+    LoadTargetNode {
+        name: usize,
+        edge: usize,
+    },
+    LoadOtherNode {
+        name: usize,
+        node: usize,
+        edge: usize,
+    },
+    /// Expands a `*min..max` hop range from `node`, following edges in
+    /// `direction` and binding every reachable endpoint to `name`. A node
+    /// already visited on the current partial path is never re-entered,
+    /// which keeps expansion finite on cyclic graphs even when `max` is
+    /// `None` (unbounded). `label`/`properties` constrain every edge
+    /// crossed during the expansion (e.g. `-[:KNOWS*1..3]->` only follows
+    /// `KNOWS` edges), mirroring `EdgeHasLabel`/`PropertyEquals` for the
+    /// fixed-hop case, since the individual edges here are never bound
+    /// to a name a later `Filter` step could apply to.
+    LoadVariableEdge {
+        name: usize,
+        node: usize,
+        min: usize,
+        max: Option<usize>,
+        direction: Direction,
+        label: Option<String>,
+        properties: Vec<(String, Value)>,
+        /// Slot standing in for the whole hop stretch in a bound `Path`'s
+        /// `elements` (see `Path`'s doc comment). Resolved the moment
+        /// `name` is, since it isn't a real binding of its own — just a
+        /// placeholder for `BindPath` to depend on.
+        path_edge: Option<usize>,
+    },
+    Filter(Filter),
+    /// Opens an `OPTIONAL MATCH` scope: the bracketed steps up to the
+    /// matching `EndOptional` run as a sub-search against each incoming
+    /// binding.
+    BeginOptional {
+        scope: usize,
+    },
+    /// Closes an optional scope. If the sub-search produced at least one
+    /// extension, its bindings are emitted; otherwise the original
+    /// binding is emitted once with every name in `bound_names` null.
+    EndOptional {
+        scope: usize,
+        bound_names: Vec<NamedValue>,
+    },
+    /// Assembles the alternating node/edge sequence recorded while
+    /// compiling a `p = (...)` clause into a [`Path`] bound to `name`.
+    BindPath {
+        name: usize,
+        elements: Vec<NamedValue>,
+    },
+    /// Terminal grouping step emitted when any `RETURN` projection is an
+    /// aggregate. `group_by` are the non-aggregate projections, taken as
+    /// the `GROUP BY` key; `aggregates` fold their argument per group.
+    Aggregate {
+        group_by: Vec<Projection>,
+        aggregates: Vec<Projection>,
+    },
+}
+
+/// One entry of a compiled `RETURN` clause. `nullable` is set when the
+/// projected name was first bound inside an `OPTIONAL MATCH` scope, so
+/// callers know to expect a null in that position.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Projection {
+    Bound {
+        value: NamedValue,
+        nullable: bool,
+    },
+    Property {
+        target: usize,
+        key: String,
+        nullable: bool,
+    },
+    /// `arg` is `None` for `count(*)`.
+    Aggregate {
+        func: AggregateFunc,
+        arg: Option<Box<Projection>>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryPlan {
+    pub steps: Vec<MatchStep>,
+    pub returns: Vec<Projection>,
+    /// Sum of the per-step cost estimates the optimizer scheduled this
+    /// plan with, for comparing candidate plans against each other.
+    pub estimated_cost: f64,
+}